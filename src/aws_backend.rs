@@ -0,0 +1,284 @@
+use crate::protos::language::{
+    Engine, ErrorType, LanguageCode, OutputFormat, SpeechMark, TextType, VoiceInfo,
+};
+use crate::synthesizer::Synthesizer;
+use crate::translator::Translator;
+use rusoto_core::region::Region;
+use rusoto_polly::{
+    DescribeVoicesError, DescribeVoicesInput, Polly, PollyClient, SynthesizeSpeechError,
+    SynthesizeSpeechInput,
+};
+use rusoto_translate::{Translate, TranslateClient, TranslateTextError, TranslateTextRequest};
+use serde::Deserialize;
+
+// SpeechMarkRecord is a single newline-delimited json record from Polly's
+// json speech mark output.
+#[derive(Deserialize)]
+struct SpeechMarkRecord {
+    time: i64,
+    #[serde(rename = "type")]
+    r#type: String,
+    start: Option<i32>,
+    end: Option<i32>,
+    value: Option<String>,
+}
+
+// AwsBackend is the Translator/Synthesizer implementation backed by AWS
+// Translate and Polly. The clients are built once (in new) and reused
+// across every request instead of being rebuilt per call, since they're
+// cheap to clone and share a connection pool.
+#[derive(Clone)]
+pub struct AwsBackend {
+    translate_client: TranslateClient,
+    polly_client: PollyClient,
+}
+
+impl AwsBackend {
+    pub fn new(region: Region) -> AwsBackend {
+        AwsBackend {
+            translate_client: TranslateClient::new(region.clone()),
+            polly_client: PollyClient::new(region),
+        }
+    }
+}
+
+impl Translator for AwsBackend {
+    fn translate_text(
+        &self,
+        source_language_code: LanguageCode,
+        target_language_code: LanguageCode,
+        text: String,
+    ) -> Result<String, ErrorType> {
+        // get translated text or map the aws error to an ErrorType
+        self.translate_client
+            .translate_text(TranslateTextRequest {
+                source_language_code: source_language_code.to_string(),
+                target_language_code: target_language_code.to_string(),
+                text: text,
+            })
+            .sync()
+            .map_err(|e| match e {
+                TranslateTextError::InternalServer(_)
+                | TranslateTextError::ServiceUnavailable(_)
+                | TranslateTextError::HttpDispatch(_)
+                | TranslateTextError::Unknown(_)
+                | TranslateTextError::ParseError(_) => ErrorType::Internal,
+                _ => ErrorType::User,
+            })
+            .map(|r| r.translated_text)
+    }
+}
+
+impl Synthesizer for AwsBackend {
+    // describe_voices returns every Polly voice available for a language so
+    // a caller can list them (list_voices) or pick the best match
+    // (resolve_voice_id) instead of blindly taking the first one back.
+    fn describe_voices(&self, language_code: String) -> Result<Vec<VoiceInfo>, ErrorType> {
+        self.polly_client
+            // describe voices is used to get a list of avaialable voices for
+            // the target language.
+            .describe_voices(DescribeVoicesInput {
+                language_code: Some(language_code),
+                next_token: None,
+                ..DescribeVoicesInput::default()
+            })
+            .sync()
+            // map the aws error to an ErrorType - propogate it down
+            .map_err(|e| match e {
+                DescribeVoicesError::InvalidNextToken(_)
+                | DescribeVoicesError::Validation(_)
+                | DescribeVoicesError::Credentials(_) => ErrorType::User,
+                _ => ErrorType::Internal,
+            })
+            .map(|dv_output| {
+                dv_output
+                    .voices
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|voice| {
+                        let mut info = VoiceInfo::new();
+                        info.name = voice.name.unwrap_or_default();
+                        info.id = voice.id.unwrap_or_default();
+                        info.gender = voice.gender.unwrap_or_default();
+                        info.supported_engines = voice
+                            .supported_engines
+                            .unwrap_or_default()
+                            .iter()
+                            .filter_map(|e| engine_from_polly(e))
+                            .collect();
+                        info
+                    })
+                    .collect()
+            })
+    }
+
+    fn synthesize_speech(
+        &self,
+        voice_id: String,
+        text: String,
+        engine: Engine,
+        output_format: OutputFormat,
+        text_type: TextType,
+    ) -> Result<Vec<u8>, ErrorType> {
+        // return audio bytes or error types
+        self.polly_client
+            .synthesize_speech(SynthesizeSpeechInput {
+                text,
+                voice_id,
+                engine: Some(polly_engine(engine)),
+                output_format: polly_output_format(output_format),
+                text_type: Some(polly_text_type(text_type)),
+                ..SynthesizeSpeechInput::default()
+            })
+            .sync()
+            .map_err(move |e| {
+                println!("synthesize error: {}", e);
+
+                match e {
+                    SynthesizeSpeechError::HttpDispatch(_)
+                    | SynthesizeSpeechError::Unknown(_)
+                    | SynthesizeSpeechError::ServiceFailure(_)
+                    | SynthesizeSpeechError::ParseError(_) => ErrorType::Internal,
+                    _ => ErrorType::User,
+                }
+            })
+            .map(|output| output.audio_stream.unwrap_or_default())
+    }
+
+    // get_speech_marks issues a second synthesize_speech call asking Polly
+    // for json speech marks instead of audio, so a client can align
+    // subtitles or drive viseme-based mouth animation. Each line of the
+    // response is an independent json object.
+    fn get_speech_marks(
+        &self,
+        voice_id: String,
+        text: String,
+        engine: Engine,
+        text_type: TextType,
+    ) -> Result<Vec<SpeechMark>, ErrorType> {
+        let audio_stream = self.polly_client
+            .synthesize_speech(SynthesizeSpeechInput {
+                text,
+                voice_id,
+                engine: Some(polly_engine(engine)),
+                output_format: "json".to_owned(),
+                text_type: Some(polly_text_type(text_type)),
+                speech_mark_types: Some(vec![
+                    "viseme".to_owned(),
+                    "word".to_owned(),
+                    "sentence".to_owned(),
+                ]),
+                ..SynthesizeSpeechInput::default()
+            })
+            .sync()
+            .map_err(|e| {
+                println!("speech marks error: {}", e);
+
+                match e {
+                    SynthesizeSpeechError::HttpDispatch(_)
+                    | SynthesizeSpeechError::Unknown(_)
+                    | SynthesizeSpeechError::ServiceFailure(_)
+                    | SynthesizeSpeechError::ParseError(_) => ErrorType::Internal,
+                    _ => ErrorType::User,
+                }
+            })
+            .map(|output| output.audio_stream.unwrap_or_default())?;
+
+        Ok(parse_speech_marks(&audio_stream))
+    }
+}
+
+// parse_speech_marks decodes Polly's newline-delimited json speech mark
+// output into SpeechMark protos, silently skipping any line that fails to
+// parse (e.g. a trailing blank line).
+fn parse_speech_marks(bytes: &[u8]) -> Vec<SpeechMark> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| serde_json::from_str::<SpeechMarkRecord>(line).ok())
+        .map(|record| {
+            let mut mark = SpeechMark::new();
+            mark.time_ms = record.time;
+            mark.field_type = record.r#type;
+            mark.start = record.start.unwrap_or(0);
+            mark.end = record.end.unwrap_or(0);
+            mark.value = record.value.unwrap_or_default();
+            mark
+        })
+        .collect()
+}
+
+fn polly_engine(engine: Engine) -> String {
+    match engine {
+        Engine::NEURAL => "neural".to_owned(),
+        Engine::STANDARD => "standard".to_owned(),
+    }
+}
+
+fn polly_output_format(output_format: OutputFormat) -> String {
+    match output_format {
+        OutputFormat::MP3 => "mp3".to_owned(),
+        OutputFormat::OGG_VORBIS => "ogg_vorbis".to_owned(),
+        OutputFormat::PCM => "pcm".to_owned(),
+    }
+}
+
+fn polly_text_type(text_type: TextType) -> String {
+    match text_type {
+        TextType::SSML => "ssml".to_owned(),
+        TextType::PLAIN => "text".to_owned(),
+    }
+}
+
+fn engine_from_polly(engine: &str) -> Option<Engine> {
+    match engine {
+        "neural" => Some(Engine::NEURAL),
+        "standard" => Some(Engine::STANDARD),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_speech_marks_decodes_each_json_line() {
+        let input = concat!(
+            r#"{"time":0,"type":"sentence","start":0,"end":11,"value":"Hello world"}"#,
+            "\n",
+            r#"{"time":6,"type":"word","start":0,"end":5,"value":"Hello"}"#,
+            "\n",
+        );
+
+        let marks = parse_speech_marks(input.as_bytes());
+
+        assert_eq!(marks.len(), 2);
+        assert_eq!(marks[0].time_ms, 0);
+        assert_eq!(marks[0].field_type, "sentence");
+        assert_eq!(marks[0].start, 0);
+        assert_eq!(marks[0].end, 11);
+        assert_eq!(marks[0].value, "Hello world");
+        assert_eq!(marks[1].time_ms, 6);
+        assert_eq!(marks[1].field_type, "word");
+        assert_eq!(marks[1].value, "Hello");
+    }
+
+    #[test]
+    fn parse_speech_marks_skips_blank_and_invalid_lines() {
+        let input = concat!(
+            r#"{"time":0,"type":"viseme","value":"p"}"#,
+            "\n",
+            "\n",
+            "not json\n",
+        );
+
+        let marks = parse_speech_marks(input.as_bytes());
+
+        assert_eq!(marks.len(), 1);
+        assert_eq!(marks[0].field_type, "viseme");
+        // viseme records omit start/end, which should default to 0
+        assert_eq!(marks[0].start, 0);
+        assert_eq!(marks[0].end, 0);
+    }
+}
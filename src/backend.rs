@@ -0,0 +1,48 @@
+use crate::aws_backend::AwsBackend;
+use crate::local_backend::LocalBackend;
+use crate::synthesizer::Synthesizer;
+use crate::translator::Translator;
+use rusoto_core::region::Region;
+use std::env;
+use std::sync::Arc;
+
+// Backend picks which Translator/Synthesizer implementation
+// LanguageService is built with, the same way an AsrEngine selector picks
+// between transcription providers: chosen once at startup so the RPC
+// handlers never have to branch on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Aws,
+    Local,
+}
+
+impl Backend {
+    // from_env reads the BACKEND env var ("aws" or "local"), defaulting to
+    // Aws so existing deployments don't have to set anything.
+    pub fn from_env() -> Backend {
+        match env::var("BACKEND").as_deref() {
+            Ok("local") => Backend::Local,
+            _ => Backend::Aws,
+        }
+    }
+
+    // build constructs the chosen backend's clients exactly once; region
+    // only matters for Aws and is ignored by Local.
+    pub fn build(
+        self,
+        region: Region,
+    ) -> (
+        Arc<dyn Translator + Send + Sync>,
+        Arc<dyn Synthesizer + Send + Sync>,
+    ) {
+        match self {
+            Backend::Aws => {
+                let aws = Arc::new(AwsBackend::new(region));
+                let translator: Arc<dyn Translator + Send + Sync> = aws.clone();
+                let synthesizer: Arc<dyn Synthesizer + Send + Sync> = aws;
+                (translator, synthesizer)
+            }
+            Backend::Local => (Arc::new(LocalBackend), Arc::new(LocalBackend)),
+        }
+    }
+}
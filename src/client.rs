@@ -1,12 +1,46 @@
 mod protos;
 
-use crate::protos::language::{LanguageCode, LanguageRequest};
+use crate::protos::language::{
+    LanguageCode, LanguageRequest, ListVoicesRequest, SpeechMark, SynthesisOptions, VoiceInfo,
+};
 use crate::protos::language_grpc::LanguageClient;
 use grpcio::{ChannelBuilder, EnvBuilder};
+use serde::Serialize;
 use std::fs;
 use std::io;
 use std::sync::Arc;
 
+// SpeechMarkJson mirrors the SpeechMark proto message for the .json
+// sidecar written alongside synthesized audio.
+#[derive(Serialize)]
+struct SpeechMarkJson {
+    time_ms: i64,
+    #[serde(rename = "type")]
+    mark_type: String,
+    start: i32,
+    end: i32,
+    value: String,
+}
+
+fn write_speech_marks_sidecar(count: i32, speech_marks: &[SpeechMark]) {
+    let marks: Vec<SpeechMarkJson> = speech_marks
+        .iter()
+        .map(|mark| SpeechMarkJson {
+            time_ms: mark.time_ms,
+            mark_type: mark.field_type.clone(),
+            start: mark.start,
+            end: mark.end,
+            value: mark.value.clone(),
+        })
+        .collect();
+
+    match serde_json::to_vec_pretty(&marks) {
+        Ok(bytes) => fs::write(format!("syn-{}.json", count), bytes)
+            .expect("failed to write speech marks"),
+        Err(e) => eprintln!("failed to serialize speech marks: {}", e),
+    }
+}
+
 // Stage represents the different stages a user can be in the application.
 // They are either picking an operation, picking a src or target language, or
 // inputing the text to translate or synthesize
@@ -15,6 +49,7 @@ enum Stage {
     OPERATION,
     SRC,
     TARGET,
+    VOICE,
     TEXT,
 }
 
@@ -149,6 +184,57 @@ fn main() {
                     }
                 };
                 if req.target_language_code != LanguageCode::UNKNOWN {
+                    stage = if op == "synthesize" {
+                        Stage::VOICE
+                    } else {
+                        Stage::TEXT
+                    };
+                }
+            }
+            Stage::VOICE => {
+                let mut list_req = ListVoicesRequest::new();
+                list_req.language_code = req.target_language_code;
+                let voices = match client.list_voices(&list_req) {
+                    Ok(resp) => resp.voices,
+                    Err(e) => {
+                        eprintln!("Not able to list voices: {}\n", e);
+                        Vec::new()
+                    }
+                };
+
+                if voices.is_empty() {
+                    stage = Stage::TEXT;
+                    // nothing was read, so skip the quit-on-empty-input check
+                    continue;
+                } else {
+                    println!(
+                        "\nWhich voice would you like to use? (0 for default)\n0 Default\n{}",
+                        voices
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, voice): (usize, &VoiceInfo)| format!(
+                                "{} {} ({}, {})",
+                                idx + 1,
+                                voice.name,
+                                voice.gender,
+                                voice.id
+                            ))
+                            .collect::<Vec<String>>()
+                            .join("\n")
+                    );
+                    io::stdin()
+                        .read_line(&mut input)
+                        .expect("error reading line");
+
+                    let mut options = SynthesisOptions::new();
+                    if let Ok(idx) = input.trim().parse::<usize>() {
+                        if idx > 0 {
+                            if let Some(voice) = voices.get(idx - 1) {
+                                options.voice_id = voice.id.clone();
+                            }
+                        }
+                    }
+                    req.synthesis_options = Some(options);
                     stage = Stage::TEXT;
                 }
             }
@@ -178,6 +264,9 @@ fn main() {
                                 "Synthesized Text, audio bytes written to syn-{}.mp3\n",
                                 count
                             );
+                            if !resp.speech_marks.is_empty() {
+                                write_speech_marks_sidecar(count, &resp.speech_marks);
+                            }
                             stage = Stage::OPERATION;
                         }
                         Err(e) => {
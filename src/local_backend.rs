@@ -0,0 +1,68 @@
+use crate::protos::language::{
+    Engine, ErrorType, LanguageCode, OutputFormat, SpeechMark, TextType, VoiceInfo,
+};
+use crate::synthesizer::Synthesizer;
+use crate::translator::Translator;
+
+const LOCAL_VOICE_ID: &str = "local-default";
+// samples/sec and bytes/sample for the silent pcm clip synthesize_speech
+// stands in with; good enough to exercise the pipeline without AWS creds.
+const SAMPLE_RATE_HZ: usize = 16000;
+const BYTES_PER_SAMPLE: usize = 2;
+const MS_PER_CHARACTER: usize = 60;
+
+// LocalBackend is a Translator/Synthesizer implementation that needs no
+// AWS credentials, so the service can run entirely offline. translate_text
+// is a passthrough (no local translation model is wired up yet) and
+// synthesize_speech produces a silent pcm clip sized to the input text;
+// both are meant as a starting point for a real offline engine, not a
+// production substitute for AWS Translate/Polly.
+#[derive(Clone, Copy)]
+pub struct LocalBackend;
+
+impl Translator for LocalBackend {
+    fn translate_text(
+        &self,
+        _source_language_code: LanguageCode,
+        _target_language_code: LanguageCode,
+        text: String,
+    ) -> Result<String, ErrorType> {
+        println!("local backend: passing text through untranslated");
+        Ok(text)
+    }
+}
+
+impl Synthesizer for LocalBackend {
+    fn describe_voices(&self, _language_code: String) -> Result<Vec<VoiceInfo>, ErrorType> {
+        let mut voice = VoiceInfo::new();
+        voice.name = "Local Default".to_owned();
+        voice.id = LOCAL_VOICE_ID.to_owned();
+        voice.gender = "NEUTRAL".to_owned();
+        voice.supported_engines = vec![Engine::STANDARD];
+        Ok(vec![voice])
+    }
+
+    fn synthesize_speech(
+        &self,
+        _voice_id: String,
+        text: String,
+        _engine: Engine,
+        _output_format: OutputFormat,
+        _text_type: TextType,
+    ) -> Result<Vec<u8>, ErrorType> {
+        let sample_count =
+            (text.chars().count() * MS_PER_CHARACTER * SAMPLE_RATE_HZ) / 1000;
+        Ok(vec![0u8; sample_count * BYTES_PER_SAMPLE])
+    }
+
+    fn get_speech_marks(
+        &self,
+        _voice_id: String,
+        _text: String,
+        _engine: Engine,
+        _text_type: TextType,
+    ) -> Result<Vec<SpeechMark>, ErrorType> {
+        // speech marks aren't modeled by the local engine yet
+        Ok(Vec::new())
+    }
+}
@@ -1,22 +1,66 @@
+mod aws_backend;
+mod backend;
+mod local_backend;
 mod protos;
+mod synthesizer;
+mod translator;
 
+use crate::backend::Backend;
 use crate::protos::language::{
-    ErrorType, LanguageCode, LanguageRequest, SynthesizeResponse, TranslateResponse,
+    live_translate_request::Payload, Engine, ErrorType, LanguageCode, LanguageRequest,
+    ListVoicesRequest, ListVoicesResponse, LiveTranslateEvent, LiveTranslateRequest, OutputFormat,
+    SynthesizeResponse, TextType, Transcription, Translation, TranslateResponse, Voice,
 };
 use crate::protos::language_grpc::{create_language, Language};
+use crate::synthesizer::Synthesizer;
+use crate::translator::Translator;
 use futures::future::Future;
-use grpcio::{self, Environment, RpcContext, RpcStatus, RpcStatusCode, ServerBuilder, UnarySink};
+use futures::{Async, Poll, Stream};
+use grpcio::{
+    self, DuplexSink, Environment, RequestStream, RpcContext, RpcStatus, RpcStatusCode,
+    ServerBuilder, UnarySink, WriteFlags,
+};
 use rusoto_core::region::Region;
-use rusoto_polly::{
-    DescribeVoicesError, DescribeVoicesInput, Polly, PollyClient, SynthesizeSpeechError,
-    SynthesizeSpeechInput,
+use rusoto_transcribestreaming::{
+    AudioEvent, AudioStream, StartStreamTranscriptionRequest, TranscribeStreamingService,
+    TranscribeStreamingServiceClient, TranscriptResultStream,
 };
-use rusoto_translate::{Translate, TranslateClient, TranslateTextError, TranslateTextRequest};
 use std::fmt;
 use std::io::Read;
+use std::str::FromStr;
 use std::sync::mpsc;
-use std::sync::Arc;
-use std::{io, thread};
+use std::sync::{Arc, Mutex};
+use std::{env, io, thread};
+// TranscribeStreamingServiceClient is built on futures 0.3/tokio, unlike
+// the Translate/Polly clients in aws_backend. futures03 is an alias for that
+// version of the crate, pulled in just to drive the transcribe event
+// stream; it never crosses into the grpcio (futures 0.1) code paths.
+use futures03::channel::mpsc as mpsc03;
+use futures03::StreamExt as _;
+
+// ChannelStream adapts a futures 0.1 mpsc receiver into the Stream shape a
+// DuplexSink expects. Unlike a std::sync::mpsc::Receiver, this one parks
+// the polling task on its own sender when empty, so send_all is properly
+// re-woken instead of relying on a busy NotReady.
+struct ChannelStream<T> {
+    rx: futures::sync::mpsc::UnboundedReceiver<T>,
+}
+
+impl<T> Stream for ChannelStream<T> {
+    type Item = (T, WriteFlags);
+    type Error = grpcio::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        match self.rx.poll() {
+            Ok(Async::Ready(Some(item))) => Ok(Async::Ready(Some((item, WriteFlags::default())))),
+            Ok(Async::Ready(None)) => Ok(Async::Ready(None)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            // UnboundedReceiver::poll never actually errors, but the Stream
+            // impl still requires handling it.
+            Err(()) => Ok(Async::Ready(None)),
+        }
+    }
+}
 
 impl fmt::Display for LanguageCode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -34,7 +78,15 @@ impl fmt::Display for LanguageCode {
 }
 
 #[derive(Clone)]
-struct LanguageService;
+struct LanguageService {
+    translator: Arc<dyn Translator + Send + Sync>,
+    synthesizer: Arc<dyn Synthesizer + Send + Sync>,
+    // live_translate's Transcribe session is orthogonal to the
+    // Translator/Synthesizer backend selection (there's no offline
+    // transcription backend yet), so the client is held directly here
+    // rather than behind a trait, built once in main and reused.
+    transcribe_client: TranscribeStreamingServiceClient,
+}
 
 impl Language for LanguageService {
     fn translate(
@@ -50,7 +102,7 @@ impl Language for LanguageService {
 
         // get translated text or error and create sending future from it
         let rpc_future =
-            match self.translate_text(req.source_language_code, req.target_language_code, req.text)
+            match self.translator.translate_text(req.source_language_code, req.target_language_code, req.text)
             {
                 Ok(translated_text) => {
                     let mut resp = TranslateResponse::new();
@@ -89,26 +141,42 @@ impl Language for LanguageService {
             return;
         }
 
-        // needed because aws expects - {ISO 639 language code}-{ISO 3166 country code}
-        let language_code = match req.target_language_code {
-            LanguageCode::ZH => "cmn-CN".to_owned(),
-            LanguageCode::EN => "en-US".to_owned(),
-            c @ _ => format!("{}-{}", c.to_string(), c.to_string().to_uppercase()),
+        let options = req.synthesis_options.clone().unwrap_or_default();
+        let engine = options.engine;
+        let output_format = options.output_format;
+        let text_type = req.text_type;
+        let language_code = polly_language_code(req.target_language_code);
+        // skip_translation treats req.text as already-translated (typically
+        // ssml, since AWS Translate would mangle the tags)
+        let translated_text_result = if req.skip_translation {
+            Ok(req.text)
+        } else {
+            self.translator.translate_text(req.source_language_code, req.target_language_code, req.text)
         };
-        let synthesize_result = self
-            // first translate text
-            .translate_text(req.source_language_code, req.target_language_code, req.text)
+        let synthesize_result = translated_text_result
             .and_then(|translated_text| {
-                self.get_voice_id(language_code)
+                self.synthesizer.resolve_voice_id(options.voice_id.clone(), language_code, engine)
                     // create rpc status from aws error -  propogate status down
                     .and_then(|voice_id| {
-                        self.synthesize_speech(voice_id, translated_text)
-                            .map(|audio_bytes| {
-                                // store mp3 bytes
-                                let mut resp = SynthesizeResponse::new();
-                                resp.audio_bytes = audio_bytes;
-                                resp
-                            })
+                        self.synthesizer.synthesize_speech(
+                            voice_id.clone(),
+                            translated_text.clone(),
+                            engine,
+                            output_format,
+                            text_type,
+                        )
+                        .and_then(|audio_bytes| {
+                            // speech marks are best-effort: if Polly can't
+                            // produce them we still return the audio
+                            let speech_marks = self
+                                .synthesizer
+                                .get_speech_marks(voice_id, translated_text, engine, text_type)
+                                .unwrap_or_default();
+                            let mut resp = SynthesizeResponse::new();
+                            resp.audio_bytes = audio_bytes;
+                            resp.speech_marks = speech_marks;
+                            Ok(resp)
+                        })
                     })
             })
             .map_err(|e| match e {
@@ -125,95 +193,336 @@ impl Language for LanguageService {
 
         ctx.spawn(send_future);
     }
+
+    fn live_translate(
+        &mut self,
+        ctx: RpcContext,
+        stream: RequestStream<LiveTranslateRequest>,
+        sink: DuplexSink<LiveTranslateEvent>,
+    ) {
+        println!("Got live_translate request");
+
+        let service = self.clone();
+        let (event_tx, event_rx) = futures::sync::mpsc::unbounded::<LiveTranslateEvent>();
+
+        thread::spawn(move || {
+            service.run_live_translate(stream, event_tx);
+        });
+
+        let send_future = sink
+            .send_all(ChannelStream { rx: event_rx })
+            .map(|_| ())
+            .map_err(|e| eprintln!("err streaming live_translate events: {}", e));
+        ctx.spawn(send_future);
+    }
+
+    fn list_voices(
+        &mut self,
+        ctx: RpcContext,
+        req: ListVoicesRequest,
+        sink: UnarySink<ListVoicesResponse>,
+    ) {
+        println!("Got list_voices request\nLanguage Code: {}", req.language_code);
+
+        let language_code = polly_language_code(req.language_code);
+        let list_result = self.synthesizer.describe_voices(language_code).map(|voices| {
+            let mut resp = ListVoicesResponse::new();
+            resp.voices = voices;
+            resp
+        });
+
+        let send_future = match list_result {
+            Ok(resp) => sink.success(resp),
+            Err(e) => {
+                let rpc_status = match e {
+                    ErrorType::User => RpcStatus::new(RpcStatusCode::InvalidArgument, None),
+                    _ => RpcStatus::new(RpcStatusCode::Internal, None),
+                };
+                sink.fail(rpc_status)
+            }
+        }
+        .map_err(|e| eprintln!("err replying: {}", e));
+
+        ctx.spawn(send_future);
+    }
 }
 
 impl LanguageService {
-    fn translate_text(
+    // run_live_translate reads the client stream to completion, forwarding
+    // audio chunks into an AWS Transcribe streaming session. Finalized
+    // transcript segments are translated and synthesized immediately and
+    // every event (partial transcript, final transcript, translation,
+    // synthesized audio) is pushed onto event_tx as soon as it is available
+    // so the caller can drive live captioning/dubbing.
+    fn run_live_translate(
         &self,
-        source_language_code: LanguageCode,
-        target_language_code: LanguageCode,
-        text: String,
-    ) -> Result<String, ErrorType> {
-        let translate_client = TranslateClient::new(Region::default());
+        stream: RequestStream<LiveTranslateRequest>,
+        event_tx: futures::sync::mpsc::UnboundedSender<LiveTranslateEvent>,
+    ) {
+        let mut stream_iter = stream.wait();
+        let (audio_tx, audio_rx) = mpsc::channel::<Vec<u8>>();
+        let (transcript_tx, transcript_rx) = mpsc::channel::<(String, bool)>();
 
-        // get translated text or error and create sending future from it
-        translate_client
-            .translate_text(TranslateTextRequest {
-                source_language_code: source_language_code.to_string(),
-                target_language_code: target_language_code.to_string(),
-                text: text,
-            })
-            .sync()
-            .map_err(|e| match e {
-                TranslateTextError::InternalServer(_)
-                | TranslateTextError::ServiceUnavailable(_)
-                | TranslateTextError::HttpDispatch(_)
-                | TranslateTextError::Unknown(_)
-                | TranslateTextError::ParseError(_) => ErrorType::Internal,
-                _ => ErrorType::User,
-            })
-            .map(|r| r.translated_text)
-    }
+        // the client is expected to send a LiveTranslateConfig as its first
+        // message; source_language_code drives the Transcribe session and
+        // target_language_code drives translation/synthesis. Default to
+        // English if the client sends audio before configuring.
+        let mut source_language_code = LanguageCode::EN;
+        let mut target_language_code = LanguageCode::EN;
+        match stream_iter.next() {
+            Some(Ok(req)) => match req.payload {
+                Some(Payload::Config(cfg)) => {
+                    source_language_code = cfg.source_language_code;
+                    target_language_code = cfg.target_language_code;
+                }
+                Some(Payload::AudioChunk(chunk)) => {
+                    let _ = audio_tx.send(chunk);
+                }
+                None => {}
+            },
+            Some(Err(e)) => {
+                eprintln!("err reading live_translate request: {}", e);
+                return;
+            }
+            None => return,
+        }
+        let languages = Arc::new(Mutex::new((source_language_code, target_language_code)));
 
-    fn synthesize_speech(&self, voice_id: String, text: String) -> Result<Vec<u8>, ErrorType> {
-        let polly_client = PollyClient::new(Region::default());
+        // drive the AWS Transcribe streaming session on its own thread so
+        // it can be fed audio as soon as chunks arrive on the client stream
+        let transcribe_client = self.transcribe_client.clone();
+        let transcribe_handle = thread::spawn(move || {
+            transcribe_stream(audio_rx, transcript_tx, transcribe_client, source_language_code)
+        });
 
-        // return audio bytes or error types
-        polly_client
-            .synthesize_speech(SynthesizeSpeechInput {
-                text,
-                voice_id,
-                output_format: "mp3".to_owned(),
-                ..SynthesizeSpeechInput::default()
-            })
-            .sync()
-            .map_err(move |e| {
-                println!("synthesize error: {}", e);
-
-                match e {
-                    SynthesizeSpeechError::HttpDispatch(_)
-                    | SynthesizeSpeechError::Unknown(_)
-                    | SynthesizeSpeechError::ServiceFailure(_)
-                    | SynthesizeSpeechError::ParseError(_) => ErrorType::Internal,
-                    _ => ErrorType::User,
+        let transcript_to_events = {
+            let service = self.clone();
+            let event_tx = event_tx.clone();
+            let languages = Arc::clone(&languages);
+            thread::spawn(move || {
+                for (text, is_final) in transcript_rx {
+                    let mut transcription = Transcription::new();
+                    transcription.text = text.clone();
+                    transcription.is_final = is_final;
+                    if event_tx.send(service.wrap_transcription(transcription)).is_err() {
+                        break;
+                    }
+
+                    if !is_final {
+                        continue;
+                    }
+
+                    let (source_language_code, target_language_code) = *languages.lock().unwrap();
+                    match service.translator.translate_text(
+                        source_language_code,
+                        target_language_code,
+                        text,
+                    ) {
+                        Ok(translated_text) => {
+                            let mut translation = Translation::new();
+                            translation.text = translated_text.clone();
+                            if event_tx.send(service.wrap_translation(translation)).is_err() {
+                                break;
+                            }
+
+                            let language_code = polly_language_code(target_language_code);
+                            let voice_result = service
+                                .synthesizer
+                                .resolve_voice_id(
+                                    String::new(),
+                                    language_code,
+                                    Engine::STANDARD,
+                                )
+                                .and_then(|voice_id| {
+                                    service.synthesizer.synthesize_speech(
+                                        voice_id,
+                                        translated_text,
+                                        Engine::STANDARD,
+                                        OutputFormat::MP3,
+                                        TextType::PLAIN,
+                                    )
+                                });
+                            if let Ok(audio_bytes) = voice_result {
+                                let mut voice = Voice::new();
+                                voice.audio_bytes = audio_bytes;
+                                if event_tx.send(service.wrap_voice(voice)).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                        Err(e) => eprintln!("err translating live segment: {:?}", e),
+                    }
                 }
             })
-            .map(|output| output.audio_stream.unwrap_or_default())
+        };
+
+        for req in stream_iter {
+            let req = match req {
+                Ok(req) => req,
+                Err(e) => {
+                    eprintln!("err reading live_translate request: {}", e);
+                    break;
+                }
+            };
+            match req.payload {
+                Some(Payload::Config(cfg)) => {
+                    *languages.lock().unwrap() =
+                        (cfg.source_language_code, cfg.target_language_code);
+                }
+                Some(Payload::AudioChunk(chunk)) => {
+                    if audio_tx.send(chunk).is_err() {
+                        break;
+                    }
+                }
+                None => {}
+            }
+        }
+
+        drop(audio_tx);
+        let _ = transcribe_handle.join();
+        let _ = transcript_to_events.join();
     }
 
-    fn get_voice_id(&self, language_code: String) -> Result<String, ErrorType> {
-        let polly_client = PollyClient::new(Region::default());
-        polly_client
-            // describe voices is used to get a list of avaialable voices for
-            // the target language.
-            .describe_voices(DescribeVoicesInput {
-                language_code: Some(language_code),
-                next_token: None,
-            })
-            .sync()
-            // create rpc status from aws error -  propogate status down
-            .map_err(|e| match e {
-                DescribeVoicesError::InvalidNextToken(_)
-                | DescribeVoicesError::Validation(_)
-                | DescribeVoicesError::Credentials(_) => ErrorType::User,
-                _ => ErrorType::Internal,
-            })
-            .map(|dv_output| {
-                // just take first voice
-                dv_output
-                    .voices
-                    .unwrap_or_default()
-                    .iter()
-                    .take(1)
-                    .map(|voice| voice.id.clone().unwrap_or_else(|| "".to_owned()))
-                    .next()
-                    .unwrap_or_else(|| "".to_owned())
-            })
+    fn wrap_transcription(&self, transcription: Transcription) -> LiveTranslateEvent {
+        let mut event = LiveTranslateEvent::new();
+        event.set_transcription(transcription);
+        event
+    }
+
+    fn wrap_translation(&self, translation: Translation) -> LiveTranslateEvent {
+        let mut event = LiveTranslateEvent::new();
+        event.set_translation(translation);
+        event
+    }
+
+    fn wrap_voice(&self, voice: Voice) -> LiveTranslateEvent {
+        let mut event = LiveTranslateEvent::new();
+        event.set_voice(voice);
+        event
+    }
+}
+
+// region_from_args reads a `--region <name>` flag, falling back to
+// Region::default() (which itself honors AWS_DEFAULT_REGION/AWS_REGION)
+// when the flag isn't passed or doesn't name a known region.
+fn region_from_args() -> Region {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg != "--region" {
+            continue;
+        }
+        match args.next().map(|value| Region::from_str(&value)) {
+            Some(Ok(region)) => return region,
+            Some(Err(e)) => eprintln!("invalid --region value: {}", e),
+            None => eprintln!("--region requires a value"),
+        }
+    }
+    Region::default()
+}
+
+// needed because aws expects - {ISO 639 language code}-{ISO 3166 country code}
+fn polly_language_code(language_code: LanguageCode) -> String {
+    match language_code {
+        LanguageCode::ZH => "cmn-CN".to_owned(),
+        LanguageCode::EN => "en-US".to_owned(),
+        c @ _ => format!("{}-{}", c.to_string(), c.to_string().to_uppercase()),
     }
 }
+
+// needed because aws transcribe expects its own {ISO 639}-{ISO 3166}
+// language codes, distinct from the sets Polly and Translate accept.
+fn transcribe_language_code(language_code: LanguageCode) -> String {
+    match language_code {
+        LanguageCode::ZH => "zh-CN".to_owned(),
+        LanguageCode::FR => "fr-FR".to_owned(),
+        LanguageCode::DE => "de-DE".to_owned(),
+        LanguageCode::PT => "pt-BR".to_owned(),
+        LanguageCode::ES => "es-US".to_owned(),
+        _ => "en-US".to_owned(),
+    }
+}
+
+// transcribe_stream drives a single AWS Transcribe streaming session,
+// feeding it audio chunks received on audio_rx and pushing
+// (transcript, is_final) segments onto transcript_tx as AWS reports them.
+fn transcribe_stream(
+    audio_rx: mpsc::Receiver<Vec<u8>>,
+    transcript_tx: mpsc::Sender<(String, bool)>,
+    transcribe_client: TranscribeStreamingServiceClient,
+    source_language_code: LanguageCode,
+) {
+    // forward chunks onto a futures03 channel as they arrive instead of
+    // collecting them into a Vec up front, so start_stream_transcription
+    // can pull (and AWS can start transcribing) as soon as the first chunk
+    // is available rather than waiting for the client to finish sending.
+    let (audio_tx03, audio_rx03) = mpsc03::unbounded::<AudioStream>();
+    thread::spawn(move || {
+        for bytes in audio_rx {
+            let event = AudioStream::AudioEvent(AudioEvent {
+                audio_chunk: Some(bytes.into()),
+            });
+            if audio_tx03.unbounded_send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    let request = StartStreamTranscriptionRequest {
+        language_code: transcribe_language_code(source_language_code),
+        media_encoding: "pcm".to_owned(),
+        media_sample_rate_hertz: 16000,
+        audio_stream: Box::pin(audio_rx03),
+        ..StartStreamTranscriptionRequest::default()
+    };
+
+    let mut runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("err creating transcribe runtime: {}", e);
+            return;
+        }
+    };
+
+    let result = runtime.block_on(async {
+        let mut output = transcribe_client.start_stream_transcription(request).await?;
+        while let Some(event) = output.transcript_result_stream.next().await {
+            match event? {
+                TranscriptResultStream::TranscriptEvent(transcript_event) => {
+                    for result in transcript_event.transcript.unwrap_or_default().results.unwrap_or_default() {
+                        let is_final = !result.is_partial.unwrap_or(false);
+                        for alt in result.alternatives.unwrap_or_default() {
+                            if let Some(text) = alt.transcript {
+                                if transcript_tx.send((text, is_final)).is_err() {
+                                    return Ok(());
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok::<(), rusoto_core::RusotoError<rusoto_transcribestreaming::StartStreamTranscriptionError>>(())
+    });
+
+    if let Err(e) = result {
+        eprintln!("err in transcribe stream: {}", e);
+    }
+}
+
 fn main() {
+    let backend = Backend::from_env();
+    let region = region_from_args();
+    println!("using {:?} backend in region {}", backend, region.name());
+    let (translator, synthesizer) = backend.build(region.clone());
+    let transcribe_client = TranscribeStreamingServiceClient::new(region);
+
     let env = Arc::new(Environment::new(1));
-    let service = create_language(LanguageService);
+    let service = create_language(LanguageService {
+        translator,
+        synthesizer,
+        transcribe_client,
+    });
     let mut server = ServerBuilder::new(env)
         .register_service(service)
         .bind("0.0.0.0", 8081)
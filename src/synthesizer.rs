@@ -0,0 +1,135 @@
+use crate::protos::language::{Engine, ErrorType, OutputFormat, SpeechMark, TextType, VoiceInfo};
+
+// Synthesizer turns text into speech (and, where supported, speech marks),
+// and lists the voices it has available for a language. Pulling this out
+// as a trait keeps LanguageService from being hardwired to rusoto_polly, so
+// handlers can run against a mock in tests or swap providers at startup
+// instead of recompiling.
+pub trait Synthesizer {
+    fn describe_voices(&self, language_code: String) -> Result<Vec<VoiceInfo>, ErrorType>;
+
+    // resolve_voice_id honors an explicitly requested voice_id and only
+    // falls back to describe_voices (preferring a voice that supports the
+    // requested engine) when the caller didn't specify one.
+    fn resolve_voice_id(
+        &self,
+        voice_id: String,
+        language_code: String,
+        engine: Engine,
+    ) -> Result<String, ErrorType> {
+        if !voice_id.is_empty() {
+            return Ok(voice_id);
+        }
+
+        self.describe_voices(language_code).map(|voices| {
+            voices
+                .iter()
+                .find(|voice| voice.supported_engines.contains(&engine))
+                .or_else(|| voices.first())
+                .map(|voice| voice.id.clone())
+                .unwrap_or_default()
+        })
+    }
+
+    fn synthesize_speech(
+        &self,
+        voice_id: String,
+        text: String,
+        engine: Engine,
+        output_format: OutputFormat,
+        text_type: TextType,
+    ) -> Result<Vec<u8>, ErrorType>;
+
+    fn get_speech_marks(
+        &self,
+        voice_id: String,
+        text: String,
+        engine: Engine,
+        text_type: TextType,
+    ) -> Result<Vec<SpeechMark>, ErrorType>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockSynthesizer {
+        voices: Vec<VoiceInfo>,
+    }
+
+    impl Synthesizer for MockSynthesizer {
+        fn describe_voices(&self, _language_code: String) -> Result<Vec<VoiceInfo>, ErrorType> {
+            Ok(self.voices.clone())
+        }
+
+        fn synthesize_speech(
+            &self,
+            _voice_id: String,
+            _text: String,
+            _engine: Engine,
+            _output_format: OutputFormat,
+            _text_type: TextType,
+        ) -> Result<Vec<u8>, ErrorType> {
+            Ok(Vec::new())
+        }
+
+        fn get_speech_marks(
+            &self,
+            _voice_id: String,
+            _text: String,
+            _engine: Engine,
+            _text_type: TextType,
+        ) -> Result<Vec<SpeechMark>, ErrorType> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn voice(id: &str, engines: Vec<Engine>) -> VoiceInfo {
+        let mut info = VoiceInfo::new();
+        info.id = id.to_owned();
+        info.supported_engines = engines;
+        info
+    }
+
+    #[test]
+    fn resolve_voice_id_honors_explicit_id() {
+        let synth = MockSynthesizer {
+            voices: vec![voice("ignored", vec![Engine::STANDARD])],
+        };
+
+        let resolved = synth
+            .resolve_voice_id("explicit".to_owned(), "en-US".to_owned(), Engine::NEURAL)
+            .unwrap();
+
+        assert_eq!(resolved, "explicit");
+    }
+
+    #[test]
+    fn resolve_voice_id_prefers_engine_match_on_fallback() {
+        let synth = MockSynthesizer {
+            voices: vec![
+                voice("standard-only", vec![Engine::STANDARD]),
+                voice("neural-capable", vec![Engine::NEURAL]),
+            ],
+        };
+
+        let resolved = synth
+            .resolve_voice_id(String::new(), "en-US".to_owned(), Engine::NEURAL)
+            .unwrap();
+
+        assert_eq!(resolved, "neural-capable");
+    }
+
+    #[test]
+    fn resolve_voice_id_falls_back_to_first_voice_when_no_engine_match() {
+        let synth = MockSynthesizer {
+            voices: vec![voice("standard-only", vec![Engine::STANDARD])],
+        };
+
+        let resolved = synth
+            .resolve_voice_id(String::new(), "en-US".to_owned(), Engine::NEURAL)
+            .unwrap();
+
+        assert_eq!(resolved, "standard-only");
+    }
+}
@@ -0,0 +1,14 @@
+use crate::protos::language::{ErrorType, LanguageCode};
+
+// Translator turns source-language text into target-language text. Pulling
+// this out as a trait keeps LanguageService from being hardwired to
+// rusoto_translate, so handlers can run against a mock in tests or swap
+// providers at startup instead of recompiling.
+pub trait Translator {
+    fn translate_text(
+        &self,
+        source_language_code: LanguageCode,
+        target_language_code: LanguageCode,
+        text: String,
+    ) -> Result<String, ErrorType>;
+}